@@ -1,9 +1,8 @@
 use snakecase::ascii::to_snakecase;
-use std::{
-    fmt::{Display, Formatter, Write},
-    process::{Command, Stdio},
-};
+use std::fmt::{Display, Formatter};
 
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
 use zbus::names::BusName;
 use zbus_xml::{Arg, ArgDirection, Interface};
 use zvariant::{
@@ -15,135 +14,374 @@ pub struct GenTrait<'i> {
     pub interface: &'i Interface<'i>,
     pub service: Option<&'i BusName<'i>>,
     pub path: Option<&'i ObjectPath<'i>>,
+    /// When set, D-Bus struct signatures (`(...)`) are emitted as named
+    /// `#[derive(Type, Serialize, Deserialize)]` structs defined alongside the trait,
+    /// instead of being flattened into anonymous Rust tuples.
+    pub struct_mode: bool,
+    /// Controls which `#[allow(clippy::..)]` attributes the generated code carries, and the
+    /// thresholds used to decide when to emit them.
+    pub lints: LintConfig,
+}
+
+/// Configuration for the clippy lint suppressions emitted on generated code.
+///
+/// The thresholds mirror clippy's own defaults so that, left untouched, the generated code is
+/// clippy-clean; callers running clippy with a customized configuration can override them to
+/// match.
+pub struct LintConfig {
+    /// Methods/signals whose parameter count (counting `&self`) exceeds this get
+    /// `#[allow(clippy::too_many_arguments)]`. Defaults to clippy's
+    /// `too-many-arguments-threshold` of 7.
+    pub too_many_arguments_threshold: usize,
+    /// Types scoring above this get `#[allow(clippy::type_complexity)]`.
+    /// Defaults to clippy's `type-complexity-threshold` of 250. Only the parameter and return
+    /// types of the trait's items are scored; the field types of structs generated in
+    /// `struct_mode` are not checked and carry no allow attribute.
+    pub type_complexity_threshold: u32,
+    /// When set, the needed allow attributes are emitted once as module-level `#![allow(..)]`
+    /// inner attributes instead of being repeated on each generated item.
+    pub module_level: bool,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            too_many_arguments_threshold: 7,
+            type_complexity_threshold: TYPE_COMPLEXITY_THRESHOLD,
+            module_level: false,
+        }
+    }
+}
+
+/// Accumulates which lints are tripped anywhere in the interface, for module-level emission.
+#[derive(Default)]
+struct LintUse {
+    too_many_arguments: bool,
+    type_complexity: bool,
+}
+
+impl LintUse {
+    fn inner_attrs(&self) -> TokenStream {
+        let mut tokens = quote! {};
+        if self.too_many_arguments {
+            tokens.extend(quote! { #![allow(clippy::too_many_arguments)] });
+        }
+        if self.type_complexity {
+            tokens.extend(quote! { #![allow(clippy::type_complexity)] });
+        }
+        tokens
+    }
 }
 
 impl<'i> Display for GenTrait<'i> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let mut unformatted = String::new();
-        self.write_interface(&mut unformatted)?;
-
-        let formatted = match format_generated_code(&unformatted) {
-            Ok(formatted) => formatted,
-            Err(e) => {
-                eprintln!("Failed to format generated code: {}", e);
-                unformatted
-            }
-        };
-
-        write!(f, "{}", formatted)
+        let tokens = self.to_tokens();
+        // The emitted tokens always form a valid `syn::File` (a single trait plus any
+        // generated structs), so this parse cannot fail in practice; should the generator ever
+        // emit malformed tokens we surface it as a formatting error rather than panicking
+        // inside a `Display` impl. `prettyplease` formats in-process, so no `rustfmt` binary is
+        // required.
+        let file = syn::parse2::<syn::File>(tokens).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", prettyplease::unparse(&file))
     }
 }
 
 impl<'i> GenTrait<'i> {
-    fn write_interface<W: Write>(&self, w: &mut W) -> std::fmt::Result {
+    /// Build the proxy trait as a [`TokenStream`].
+    ///
+    /// Callers that want the generated AST rather than a formatted string can use this
+    /// directly; the [`Display`] impl simply formats the result with `prettyplease`.
+    pub fn to_tokens(&self) -> TokenStream {
         let iface = self.interface;
         let idx = iface.name().rfind('.').unwrap() + 1;
-        let name = &iface.name()[idx..];
+        let name = format_ident!("{}", &iface.name()[idx..]);
 
-        write!(w, "#[proxy(interface = \"{}\"", iface.name())?;
+        let iface_name = iface.name().to_string();
+        let mut proxy_args = quote! { interface = #iface_name };
         if let Some(service) = self.service {
-            write!(w, ", default_service = \"{service}\"")?;
+            let service = service.to_string();
+            proxy_args.extend(quote! { , default_service = #service });
         }
         if let Some(path) = self.path {
-            write!(w, ", default_path = \"{path}\"")?;
+            let path = path.to_string();
+            proxy_args.extend(quote! { , default_path = #path });
         }
         if self.path.is_none() || self.service.is_none() {
-            write!(w, ", assume_defaults = true")?;
+            proxy_args.extend(quote! { , assume_defaults = true });
         }
-        writeln!(w, ")]")?;
-        writeln!(w, "trait {name} {{")?;
+
+        // When `struct_mode` is set, struct signatures are collected here as named types and
+        // emitted after the trait; otherwise they are flattened into anonymous tuples.
+        let mut structs = self.struct_mode.then(StructCollector::default);
+        // In module-level mode, per-item attributes are suppressed and the tripped lints are
+        // recorded here so a single `#![allow(..)]` block can be emitted at the top.
+        let mut lint_use = LintUse::default();
 
         let mut methods = iface.methods().to_vec();
         methods.sort_by(|a, b| a.name().partial_cmp(&b.name()).unwrap());
-        for m in &methods {
-            let (inputs, output) = inputs_output_from_args(m.args());
-            let name = to_identifier(&to_snakecase(m.name().as_str()));
-            writeln!(w)?;
-            writeln!(w, "    /// {} method", m.name())?;
-            if pascal_case(&name) != m.name().as_str() {
-                writeln!(w, "    #[zbus(name = \"{}\")]", m.name())?;
-            }
-            hide_clippy_lints(w, m)?;
-            writeln!(w, "    fn {name}({inputs}){output};")?;
-        }
+        let methods: Vec<_> = methods
+            .iter()
+            .map(|m| self.gen_method(m, &mut structs, &mut lint_use))
+            .collect();
 
         let mut signals = iface.signals().to_vec();
         signals.sort_by(|a, b| a.name().partial_cmp(&b.name()).unwrap());
-        for signal in &signals {
-            let args = parse_signal_args(signal.args());
-            let name = to_identifier(&to_snakecase(signal.name().as_str()));
-            writeln!(w)?;
-            writeln!(w, "    /// {} signal", signal.name())?;
-            if pascal_case(&name) != signal.name().as_str() {
-                writeln!(w, "    #[zbus(signal, name = \"{}\")]", signal.name())?;
-            } else {
-                writeln!(w, "    #[zbus(signal)]")?;
-            }
-            writeln!(w, "    fn {name}({args}) -> zbus::Result<()>;",)?;
-        }
+        let signals: Vec<_> = signals
+            .iter()
+            .map(|s| self.gen_signal(s, &mut structs, &mut lint_use))
+            .collect();
 
         let mut props = iface.properties().to_vec();
         props.sort_by(|a, b| a.name().partial_cmp(&b.name()).unwrap());
-        for p in props {
-            let name = to_identifier(&to_snakecase(p.name().as_str()));
-            let fn_attribute = if pascal_case(&name) != p.name().as_str() {
-                format!("    #[zbus(property, name = \"{}\")]", p.name())
-            } else {
-                "    #[zbus(property)]".to_string()
-            };
-
-            writeln!(w)?;
-            writeln!(w, "    /// {} property", p.name())?;
-            if p.access().read() {
-                writeln!(w, "{}", fn_attribute)?;
-                let output = to_rust_type(p.ty(), false, false);
-                hide_clippy_type_complexity_lint(w, p.ty().signature())?;
-                writeln!(w, "    fn {name}(&self) -> zbus::Result<{output}>;",)?;
-            }
+        let props: Vec<_> = props
+            .iter()
+            .map(|p| self.gen_property(p, &mut structs, &mut lint_use))
+            .collect();
+
+        let structs = structs.map(|c| c.definitions()).unwrap_or_default();
+        let module_attrs = if self.lints.module_level {
+            lint_use.inner_attrs()
+        } else {
+            quote! {}
+        };
 
-            if p.access().write() {
-                writeln!(w, "{}", fn_attribute)?;
-                let input = to_rust_type(p.ty(), true, true);
-                writeln!(
-                    w,
-                    "    fn set_{name}(&self, value: {input}) -> zbus::Result<()>;",
-                )?;
+        quote! {
+            #module_attrs
+
+            #[proxy(#proxy_args)]
+            trait #name {
+                #(#methods)*
+                #(#signals)*
+                #(#props)*
             }
+
+            #structs
         }
-        writeln!(w, "}}")
     }
-}
 
-fn hide_clippy_lints<W: Write>(write: &mut W, method: &zbus_xml::Method<'_>) -> std::fmt::Result {
-    // check for <https://rust-lang.github.io/rust-clippy/master/index.html#/too_many_arguments>
-    // triggers when a functions has at least 7 paramters
-    if method.args().len() >= 7 {
-        writeln!(write, "    #[allow(clippy::too_many_arguments)]")?;
+    fn gen_method(
+        &self,
+        m: &zbus_xml::Method<'_>,
+        structs: &mut Option<StructCollector>,
+        lint_use: &mut LintUse,
+    ) -> TokenStream {
+        let (inputs, output) = inputs_output_from_args(m.args(), structs);
+        let name = to_identifier(&to_snakecase(m.name().as_str()));
+        let ident = format_ident!("{name}");
+        let doc = format!(" {} method", m.name());
+
+        let rename = if pascal_case(&name) != m.name().as_str() {
+            let orig = m.name().as_str();
+            quote! { #[zbus(name = #orig)] }
+        } else {
+            quote! {}
+        };
+        let lints = self.hide_clippy_lints(m, structs.as_ref(), lint_use);
+
+        quote! {
+            #[doc = #doc]
+            #rename
+            #lints
+            fn #ident(#(#inputs),*) -> zbus::Result<#output>;
+        }
     }
 
-    // check for <https://rust-lang.github.io/rust-clippy/master/index.html#/type_complexity>
-    for arg in method.args() {
-        let signature = arg.ty().signature();
-        hide_clippy_type_complexity_lint(write, signature)?;
+    fn gen_signal(
+        &self,
+        signal: &zbus_xml::Signal<'_>,
+        structs: &mut Option<StructCollector>,
+        lint_use: &mut LintUse,
+    ) -> TokenStream {
+        let args = parse_signal_args(signal.args(), structs);
+        let name = to_identifier(&to_snakecase(signal.name().as_str()));
+        let ident = format_ident!("{name}");
+        let doc = format!(" {} signal", signal.name());
+
+        let attr = if pascal_case(&name) != signal.name().as_str() {
+            let orig = signal.name().as_str();
+            quote! { #[zbus(signal, name = #orig)] }
+        } else {
+            quote! { #[zbus(signal)] }
+        };
+
+        // All signal args are parameters, so they trip the same lints as method inputs.
+        let params: Vec<&Arg> = signal.args().iter().collect();
+        let lints = self.param_lints(&params, structs.as_ref(), lint_use);
+
+        quote! {
+            #[doc = #doc]
+            #attr
+            #lints
+            fn #ident(#(#args),*) -> zbus::Result<()>;
+        }
     }
 
-    Ok(())
+    fn gen_property(
+        &self,
+        p: &zbus_xml::Property<'_>,
+        structs: &mut Option<StructCollector>,
+        lint_use: &mut LintUse,
+    ) -> TokenStream {
+        let name = to_identifier(&to_snakecase(p.name().as_str()));
+        let ident = format_ident!("{name}");
+        let doc = format!(" {} property", p.name());
+        let attr = if pascal_case(&name) != p.name().as_str() {
+            let orig = p.name().as_str();
+            quote! { #[zbus(property, name = #orig)] }
+        } else {
+            quote! { #[zbus(property)] }
+        };
+
+        // The property doc is emitted once, on the first generated item (as in the baseline):
+        // the getter when the property is readable, otherwise the setter.
+        let read = p.access().read();
+        let mut tokens = quote! {};
+        if read {
+            let output = rust_type(p.ty(), false, false, structs);
+            let lint = self.hide_clippy_type_complexity_lint(
+                p.ty().signature().as_bytes(),
+                structs.as_ref(),
+                lint_use,
+            );
+            tokens.extend(quote! {
+                #[doc = #doc]
+                #attr
+                #lint
+                fn #ident(&self) -> zbus::Result<#output>;
+            });
+        }
+        if p.access().write() {
+            let setter = format_ident!("set_{name}");
+            let input = rust_type(p.ty(), true, true, structs);
+            let lint = self.hide_clippy_type_complexity_lint(
+                p.ty().signature().as_bytes(),
+                structs.as_ref(),
+                lint_use,
+            );
+            let doc = if read { quote! {} } else { quote! { #[doc = #doc] } };
+            tokens.extend(quote! {
+                #doc
+                #attr
+                #lint
+                fn #setter(&self, value: #input) -> zbus::Result<()>;
+            });
+        }
+        tokens
+    }
 }
 
-fn hide_clippy_type_complexity_lint<W: Write>(
-    write: &mut W,
-    signature: &zvariant::Signature,
-) -> std::fmt::Result {
-    let mut it = signature.as_bytes().iter().peekable();
-    let complexity = estimate_type_complexity(&mut it);
-    if complexity >= 1700 {
-        writeln!(write, "    #[allow(clippy::type_complexity)]")?;
+impl<'i> GenTrait<'i> {
+    fn hide_clippy_lints(
+        &self,
+        method: &zbus_xml::Method<'_>,
+        known: Option<&StructCollector>,
+        lint_use: &mut LintUse,
+    ) -> TokenStream {
+        // Only `in` (and direction-less) args become parameters; `out` args form the return
+        // tuple and are not counted towards `too_many_arguments`.
+        let params: Vec<&Arg> = method
+            .args()
+            .iter()
+            .filter(|a| !matches!(a.direction(), Some(ArgDirection::Out)))
+            .collect();
+        let mut tokens = self.param_lints(&params, known, lint_use);
+
+        // check for <https://rust-lang.github.io/rust-clippy/master/index.html#/type_complexity>
+        // on a multi-output tuple return type, in addition to the parameters above
+        if let Some(output) = multi_output_signature(method.args()) {
+            tokens.extend(self.hide_clippy_type_complexity_lint(output.as_bytes(), known, lint_use));
+        }
+
+        tokens
     }
-    Ok(())
+
+    /// Emit the `too_many_arguments`/`type_complexity` allows tripped by a parameter list,
+    /// shared by generated methods and signals.
+    fn param_lints(
+        &self,
+        params: &[&Arg],
+        known: Option<&StructCollector>,
+        lint_use: &mut LintUse,
+    ) -> TokenStream {
+        let mut tokens = quote! {};
+
+        // check for <https://rust-lang.github.io/rust-clippy/master/index.html#/too_many_arguments>
+        // triggers when the parameter count, including the `&self` receiver every generated
+        // method and signal takes, exceeds `too_many_arguments_threshold`
+        if params.len() + 1 > self.lints.too_many_arguments_threshold {
+            tokens.extend(self.allow(Lint::TooManyArguments, lint_use));
+        }
+
+        // check for <https://rust-lang.github.io/rust-clippy/master/index.html#/type_complexity>
+        // on each parameter
+        for arg in params {
+            tokens.extend(self.hide_clippy_type_complexity_lint(
+                arg.ty().signature().as_bytes(),
+                known,
+                lint_use,
+            ));
+        }
+
+        tokens
+    }
+
+    fn hide_clippy_type_complexity_lint(
+        &self,
+        signature: &[u8],
+        known: Option<&StructCollector>,
+        lint_use: &mut LintUse,
+    ) -> TokenStream {
+        if estimate_type_complexity(signature, known) > self.lints.type_complexity_threshold {
+            self.allow(Lint::TypeComplexity, lint_use)
+        } else {
+            quote! {}
+        }
+    }
+
+    /// Emit a per-item `#[allow(..)]` attribute, or — in module-level mode — record the lint
+    /// for the single `#![allow(..)]` block and emit nothing here.
+    fn allow(&self, lint: Lint, lint_use: &mut LintUse) -> TokenStream {
+        match lint {
+            Lint::TooManyArguments => {
+                lint_use.too_many_arguments = true;
+                if self.lints.module_level {
+                    return quote! {};
+                }
+                quote! { #[allow(clippy::too_many_arguments)] }
+            }
+            Lint::TypeComplexity => {
+                lint_use.type_complexity = true;
+                if self.lints.module_level {
+                    return quote! {};
+                }
+                quote! { #[allow(clippy::type_complexity)] }
+            }
+        }
+    }
+}
+
+enum Lint {
+    TooManyArguments,
+    TypeComplexity,
+}
+
+/// The combined signature of a method's output arguments, but only when there is more than
+/// one — that is the case where the return type becomes a tuple and can trip
+/// `clippy::type_complexity`.
+fn multi_output_signature(args: &[Arg]) -> Option<String> {
+    let outputs: Vec<_> = args
+        .iter()
+        .filter(|a| matches!(a.direction(), Some(ArgDirection::Out)))
+        .map(|a| a.ty().signature().to_string())
+        .collect();
+    (outputs.len() > 1).then(|| format!("({})", outputs.concat()))
 }
 
-fn inputs_output_from_args(args: &[Arg]) -> (String, String) {
-    let mut inputs = vec!["&self".to_string()];
+fn inputs_output_from_args(
+    args: &[Arg],
+    structs: &mut Option<StructCollector>,
+) -> (Vec<TokenStream>, TokenStream) {
+    let mut inputs = vec![quote! { &self }];
     let mut output = vec![];
     let mut n = 0;
     let mut gen_name = || {
@@ -154,17 +392,16 @@ fn inputs_output_from_args(args: &[Arg]) -> (String, String) {
     for a in args {
         match a.direction() {
             None | Some(ArgDirection::In) => {
-                let ty = to_rust_type(a.ty(), true, true);
-                let arg = if let Some(name) = a.name() {
-                    to_identifier(name)
-                } else {
-                    gen_name()
-                };
-                inputs.push(format!("{arg}: {ty}"));
+                let ty = rust_type(a.ty(), true, true, structs);
+                let arg = a
+                    .name()
+                    .map(to_identifier)
+                    .unwrap_or_else(&mut gen_name);
+                let arg = format_ident!("{arg}");
+                inputs.push(quote! { #arg: #ty });
             }
             Some(ArgDirection::Out) => {
-                let ty = to_rust_type(a.ty(), false, false);
-                output.push(ty);
+                output.push(rust_type_str(a.ty(), false, false, structs));
             }
         }
     }
@@ -175,11 +412,11 @@ fn inputs_output_from_args(args: &[Arg]) -> (String, String) {
         _ => format!("({})", output.join(", ")),
     };
 
-    (inputs.join(", "), format!(" -> zbus::Result<{output}>"))
+    (inputs, type_tokens(&output))
 }
 
-fn parse_signal_args(args: &[Arg]) -> String {
-    let mut inputs = vec!["&self".to_string()];
+fn parse_signal_args(args: &[Arg], structs: &mut Option<StructCollector>) -> Vec<TokenStream> {
+    let mut inputs = vec![quote! { &self }];
     let mut n = 0;
     let mut gen_name = || {
         n += 1;
@@ -187,119 +424,230 @@ fn parse_signal_args(args: &[Arg]) -> String {
     };
 
     for a in args {
-        let ty = to_rust_type(a.ty(), true, false);
-        let arg = if let Some(name) = a.name() {
-            to_identifier(name)
-        } else {
-            gen_name()
-        };
-        inputs.push(format!("{arg}: {ty}"));
+        let ty = rust_type(a.ty(), true, false, structs);
+        let arg = a
+            .name()
+            .map(to_identifier)
+            .unwrap_or_else(&mut gen_name);
+        let arg = format_ident!("{arg}");
+        inputs.push(quote! { #arg: #ty });
     }
 
-    inputs.join(", ")
+    inputs
 }
 
-fn to_rust_type(ty: &CompleteType, input: bool, as_ref: bool) -> String {
-    // can't haz recursive closure, yet
-    fn iter_to_rust_type(
-        it: &mut std::iter::Peekable<std::slice::Iter<'_, u8>>,
-        input: bool,
-        as_ref: bool,
-    ) -> String {
-        let c = it.next().unwrap();
-        match *c as char {
-            u8::SIGNATURE_CHAR => "u8".into(),
-            bool::SIGNATURE_CHAR => "bool".into(),
-            i16::SIGNATURE_CHAR => "i16".into(),
-            u16::SIGNATURE_CHAR => "u16".into(),
-            i32::SIGNATURE_CHAR => "i32".into(),
-            u32::SIGNATURE_CHAR => "u32".into(),
-            i64::SIGNATURE_CHAR => "i64".into(),
-            u64::SIGNATURE_CHAR => "u64".into(),
-            f64::SIGNATURE_CHAR => "f64".into(),
-            // xmlgen accepts 'h' on Windows, only for code generation
-            'h' => (if input {
-                "zbus::zvariant::Fd<'_>"
+/// Tokenize the string produced by [`rust_type_str`] for interpolation into the output.
+fn rust_type(
+    ty: &CompleteType,
+    input: bool,
+    as_ref: bool,
+    structs: &mut Option<StructCollector>,
+) -> TokenStream {
+    type_tokens(&rust_type_str(ty, input, as_ref, structs))
+}
+
+/// Render `ty` as a Rust type string, collecting named structs when `structs` is set.
+fn rust_type_str(
+    ty: &CompleteType,
+    input: bool,
+    as_ref: bool,
+    structs: &mut Option<StructCollector>,
+) -> String {
+    let sig = ty.signature();
+    let mut pos = 0;
+    walk_rust_type(sig.as_bytes(), &mut pos, input, as_ref, structs)
+}
+
+/// Lex a type string emitted by [`walk_rust_type`] into tokens for quoting.
+///
+/// Only tokenization happens here, which does not fail for the well-formed type strings this
+/// module produces. A semantically malformed type is not rejected at this point but when
+/// [`Display`] parses the whole generated output into a `syn::File`, where it surfaces as a
+/// `fmt::Error` instead of a panic.
+fn type_tokens(ty: &str) -> TokenStream {
+    ty.parse().unwrap_or_else(|_| quote! { () })
+}
+
+/// Walk a D-Bus signature starting at `*pos` and render the corresponding Rust type, advancing
+/// `*pos` past the bytes consumed.
+///
+/// This is the single source of truth for the signature→Rust-type mapping. When `structs` is
+/// `Some`, struct signatures (`(...)`) are interned as named types via [`StructCollector`] and
+/// referenced by name; when it is `None` they are flattened into anonymous tuples, preserving
+/// the original behaviour.
+fn walk_rust_type(
+    b: &[u8],
+    pos: &mut usize,
+    input: bool,
+    as_ref: bool,
+    structs: &mut Option<StructCollector>,
+) -> String {
+    let c = b[*pos];
+    *pos += 1;
+    match c as char {
+        u8::SIGNATURE_CHAR => "u8".into(),
+        bool::SIGNATURE_CHAR => "bool".into(),
+        i16::SIGNATURE_CHAR => "i16".into(),
+        u16::SIGNATURE_CHAR => "u16".into(),
+        i32::SIGNATURE_CHAR => "i32".into(),
+        u32::SIGNATURE_CHAR => "u32".into(),
+        i64::SIGNATURE_CHAR => "i64".into(),
+        u64::SIGNATURE_CHAR => "u64".into(),
+        f64::SIGNATURE_CHAR => "f64".into(),
+        // xmlgen accepts 'h' on Windows, only for code generation
+        'h' => (if input {
+            "zbus::zvariant::Fd<'_>"
+        } else {
+            "zbus::zvariant::OwnedFd"
+        })
+        .into(),
+        <&str>::SIGNATURE_CHAR => (if input || as_ref { "&str" } else { "String" }).into(),
+        ObjectPath::SIGNATURE_CHAR => (if input {
+            if as_ref {
+                "&zbus::zvariant::ObjectPath<'_>"
             } else {
-                "zbus::zvariant::OwnedFd"
-            })
-            .into(),
-            <&str>::SIGNATURE_CHAR => (if input || as_ref { "&str" } else { "String" }).into(),
-            ObjectPath::SIGNATURE_CHAR => (if input {
-                if as_ref {
-                    "&zbus::zvariant::ObjectPath<'_>"
-                } else {
-                    "zbus::zvariant::ObjectPath<'_>"
-                }
+                "zbus::zvariant::ObjectPath<'_>"
+            }
+        } else {
+            "zbus::zvariant::OwnedObjectPath"
+        })
+        .into(),
+        Signature::SIGNATURE_CHAR => (if input {
+            if as_ref {
+                "&zbus::zvariant::Signature<'_>"
             } else {
-                "zbus::zvariant::OwnedObjectPath"
-            })
-            .into(),
-            Signature::SIGNATURE_CHAR => (if input {
-                if as_ref {
-                    "&zbus::zvariant::Signature<'_>"
+                "zbus::zvariant::Signature<'_>"
+            }
+        } else {
+            "zbus::zvariant::OwnedSignature"
+        })
+        .into(),
+        VARIANT_SIGNATURE_CHAR => (if input {
+            if as_ref {
+                "&zbus::zvariant::Value<'_>"
+            } else {
+                "zbus::zvariant::Value<'_>"
+            }
+        } else {
+            "zbus::zvariant::OwnedValue"
+        })
+        .into(),
+        ARRAY_SIGNATURE_CHAR => match b[*pos] as char {
+            '{' => format!(
+                "std::collections::HashMap<{}>",
+                walk_rust_type(b, pos, input, false, structs)
+            ),
+            _ => {
+                let ty = walk_rust_type(b, pos, input, false, structs);
+                if input {
+                    format!("&[{ty}]")
                 } else {
-                    "zbus::zvariant::Signature<'_>"
+                    format!("{}Vec<{}>", if as_ref { "&" } else { "" }, ty)
                 }
-            } else {
-                "zbus::zvariant::OwnedSignature"
-            })
-            .into(),
-            VARIANT_SIGNATURE_CHAR => (if input {
+            }
+        },
+        DICT_ENTRY_SIG_START_CHAR => {
+            // The dict entry maps onto `HashMap`'s generic arguments.
+            let mut vec = vec![];
+            while b[*pos] as char != DICT_ENTRY_SIG_END_CHAR {
+                vec.push(walk_rust_type(b, pos, input, false, structs));
+            }
+            *pos += 1; // consume the closing character
+            vec.join(", ")
+        }
+        STRUCT_SIG_START_CHAR => {
+            let start = *pos - 1;
+            if structs.is_some() {
+                // Fields of a derived struct always own their data. D-Bus struct members carry
+                // no names in the introspection type model, so fields are named positionally
+                // (`field_1`, `field_2`, ..); there is no XML annotation to derive them from.
+                let mut fields = vec![];
+                while b[*pos] as char != STRUCT_SIG_END_CHAR {
+                    let ty = walk_rust_type(b, pos, false, false, structs);
+                    fields.push((format!("field_{}", fields.len() + 1), ty));
+                }
+                *pos += 1; // consume the closing character
+                let signature = String::from_utf8_lossy(&b[start..*pos]).into_owned();
+                // The recursion above is finished, so the collector is free to borrow again.
+                let name = structs.as_mut().unwrap().intern(signature, fields);
                 if as_ref {
-                    "&zbus::zvariant::Value<'_>"
+                    format!("&{name}")
                 } else {
-                    "zbus::zvariant::Value<'_>"
+                    name
                 }
             } else {
-                "zbus::zvariant::OwnedValue"
-            })
-            .into(),
-            ARRAY_SIGNATURE_CHAR => {
-                let c = it.peek().unwrap();
-                match **c as char {
-                    '{' => format!(
-                        "std::collections::HashMap<{}>",
-                        iter_to_rust_type(it, input, false)
-                    ),
-                    _ => {
-                        let ty = iter_to_rust_type(it, input, false);
-                        if input {
-                            format!("&[{ty}]")
-                        } else {
-                            format!("{}Vec<{}>", if as_ref { "&" } else { "" }, ty)
-                        }
-                    }
-                }
-            }
-            c @ STRUCT_SIG_START_CHAR | c @ DICT_ENTRY_SIG_START_CHAR => {
-                let dict = c == '{';
                 let mut vec = vec![];
-                loop {
-                    let c = it.peek().unwrap();
-                    match **c as char {
-                        STRUCT_SIG_END_CHAR | DICT_ENTRY_SIG_END_CHAR => {
-                            // consume the closing character
-                            it.next().unwrap();
-                            break;
-                        }
-                        _ => vec.push(iter_to_rust_type(it, input, false)),
-                    }
+                while b[*pos] as char != STRUCT_SIG_END_CHAR {
+                    vec.push(walk_rust_type(b, pos, input, false, structs));
                 }
-                if dict {
-                    vec.join(", ")
-                } else if vec.len() > 1 {
+                *pos += 1; // consume the closing character
+                if vec.len() > 1 {
                     format!("{}({})", if as_ref { "&" } else { "" }, vec.join(", "))
                 } else {
                     format!("{}({},)", if as_ref { "&" } else { "" }, vec[0])
                 }
             }
-            _ => unimplemented!(),
         }
+        _ => unimplemented!(),
+    }
+}
+
+/// A named Rust struct synthesized for a D-Bus struct signature.
+struct GenStruct {
+    /// The D-Bus signature this struct was generated from, e.g. `(usb)`.
+    signature: String,
+    name: String,
+    /// `(field name, field type)` pairs, in signature order.
+    fields: Vec<(String, String)>,
+}
+
+/// Collects the distinct D-Bus struct signatures encountered while walking an interface and
+/// synthesizes a named Rust struct for each, so that `(...)` signatures are rendered as
+/// readable named types instead of anonymous tuples. Identical signatures reuse a single
+/// struct, and nested structs yield their own nested named types.
+#[derive(Default)]
+struct StructCollector {
+    structs: Vec<GenStruct>,
+}
+
+impl StructCollector {
+    /// Whether a struct has already been interned for `signature`.
+    fn contains(&self, signature: &str) -> bool {
+        self.structs.iter().any(|s| s.signature == signature)
+    }
+
+    /// Register a struct for `signature`, reusing an existing one if the signature repeats.
+    fn intern(&mut self, signature: String, fields: Vec<(String, String)>) -> String {
+        if let Some(existing) = self.structs.iter().find(|s| s.signature == signature) {
+            return existing.name.clone();
+        }
+        let name = format!("Struct{}", self.structs.len() + 1);
+        self.structs.push(GenStruct {
+            signature,
+            name: name.clone(),
+            fields,
+        });
+        name
     }
 
-    let mut it = ty.signature().as_bytes().iter().peekable();
-    iter_to_rust_type(&mut it, input, as_ref)
+    /// Emit the collected structs as token definitions, in the order they were discovered.
+    fn definitions(&self) -> TokenStream {
+        let defs = self.structs.iter().map(|s| {
+            let name = format_ident!("{}", s.name);
+            let fields = s.fields.iter().map(|(field, ty)| {
+                let field = format_ident!("{field}");
+                let ty = type_tokens(ty);
+                quote! { pub #field: #ty }
+            });
+            quote! {
+                #[derive(zbus::zvariant::Type, serde::Serialize, serde::Deserialize)]
+                pub struct #name {
+                    #(#fields),*
+                }
+            }
+        });
+        quote! { #(#defs)* }
+    }
 }
 
 static KWORDS: &[&str] = &[
@@ -335,75 +683,197 @@ pub fn pascal_case(s: &str) -> String {
     pascal
 }
 
-fn estimate_type_complexity(it: &mut std::iter::Peekable<std::slice::Iter<'_, u8>>) -> u32 {
-    let mut score = 0;
-    let c = it.next().unwrap();
-    match *c as char {
-        u8::SIGNATURE_CHAR
-        | bool::SIGNATURE_CHAR
-        | i16::SIGNATURE_CHAR
-        | u16::SIGNATURE_CHAR
-        | i32::SIGNATURE_CHAR
-        | u32::SIGNATURE_CHAR
-        | i64::SIGNATURE_CHAR
-        | u64::SIGNATURE_CHAR
-        | f64::SIGNATURE_CHAR
-        | <&str>::SIGNATURE_CHAR => {
-            score += 1;
-        }
-        'h' => score += 10,
-        Signature::SIGNATURE_CHAR | VARIANT_SIGNATURE_CHAR | ObjectPath::SIGNATURE_CHAR => {
-            score *= 10
-        }
-        ARRAY_SIGNATURE_CHAR => {
-            let c = it.peek().unwrap();
-            match **c as char {
-                '{' => {
-                    score *= 10;
-                    score += estimate_type_complexity(it);
+/// clippy's default `type-complexity-threshold`; a type scoring above this trips the lint.
+pub const TYPE_COMPLEXITY_THRESHOLD: u32 = 250;
+
+/// Estimate the `clippy::type_complexity` score of the Rust type that [`walk_rust_type`]
+/// would emit for `signature`.
+///
+/// This mirrors clippy's `TypeComplexityVisitor`: it keeps a running `score` and a `nest`
+/// factor (starting at 1), adds `nest` for every type node visited, and multiplies `nest`
+/// by 10 while descending into the inner types of a container (the element of a `Vec`/slice,
+/// the key and value of a `HashMap`, the fields of a tuple). The walk follows the same
+/// structure as [`walk_rust_type`] so the score reflects the emitted Rust type rather than the
+/// raw D-Bus signature.
+///
+/// When `known` is `Some` (i.e. `struct_mode`), a struct signature that has been interned as a
+/// named type is scored as a single node — the emitted type is a bare `StructN`, not a tuple —
+/// rather than being descended into.
+fn estimate_type_complexity(signature: &[u8], known: Option<&StructCollector>) -> u32 {
+    fn visit(
+        b: &[u8],
+        pos: &mut usize,
+        score: &mut u32,
+        nest: u32,
+        known: Option<&StructCollector>,
+    ) {
+        let c = b[*pos];
+        *pos += 1;
+        match c as char {
+            u8::SIGNATURE_CHAR
+            | bool::SIGNATURE_CHAR
+            | i16::SIGNATURE_CHAR
+            | u16::SIGNATURE_CHAR
+            | i32::SIGNATURE_CHAR
+            | u32::SIGNATURE_CHAR
+            | i64::SIGNATURE_CHAR
+            | u64::SIGNATURE_CHAR
+            | f64::SIGNATURE_CHAR
+            | <&str>::SIGNATURE_CHAR
+            | 'h'
+            | Signature::SIGNATURE_CHAR
+            | VARIANT_SIGNATURE_CHAR
+            | ObjectPath::SIGNATURE_CHAR => {
+                *score += nest;
+            }
+            ARRAY_SIGNATURE_CHAR => {
+                // `Vec<T>`/`&[T]`, or `HashMap<K, V>` for a dict-entry element. The container
+                // itself is one node; its inner types are visited with a 10x nest factor.
+                *score += nest;
+                visit(b, pos, score, nest * 10, known);
+            }
+            STRUCT_SIG_START_CHAR => {
+                let start = *pos - 1;
+                let end = matching_struct_end(b, start);
+                let signature = String::from_utf8_lossy(&b[start..end]);
+                if known.is_some_and(|k| k.contains(&signature)) {
+                    // Emitted as a single named `StructN`: one node, no descent.
+                    *score += nest;
+                    *pos = end;
+                } else {
+                    // Tuple `(a, b, ..)`: the tuple is one node, each field nested.
+                    *score += nest;
+                    while b[*pos] as char != STRUCT_SIG_END_CHAR {
+                        visit(b, pos, score, nest * 10, known);
+                    }
+                    *pos += 1; // consume the closing character
                 }
-                _ => {
-                    score += 5 * estimate_type_complexity(it);
+            }
+            DICT_ENTRY_SIG_START_CHAR => {
+                // The dict entry maps onto `HashMap`'s generic arguments, so it adds no node
+                // of its own; the key and value are visited at the nest factor passed in.
+                while b[*pos] as char != DICT_ENTRY_SIG_END_CHAR {
+                    visit(b, pos, score, nest, known);
                 }
+                *pos += 1; // consume the closing character
             }
+            _ => {}
         }
-        STRUCT_SIG_START_CHAR | DICT_ENTRY_SIG_START_CHAR => {
-            score += 50;
-            loop {
-                let c = it.peek().unwrap();
-                match **c as char {
-                    STRUCT_SIG_END_CHAR | DICT_ENTRY_SIG_END_CHAR => {
-                        // consume the closing character
-                        it.next().unwrap();
-                        break;
-                    }
-                    _ => score += 5 * estimate_type_complexity(it),
+    }
+
+    let mut pos = 0;
+    let mut score = 0;
+    visit(signature, &mut pos, &mut score, 1, known);
+    score
+}
+
+/// Index just past the `)` matching the `(` at `start`.
+fn matching_struct_end(b: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    loop {
+        match b[i] as char {
+            STRUCT_SIG_START_CHAR => depth += 1,
+            STRUCT_SIG_END_CHAR => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
                 }
             }
+            _ => {}
         }
-        _ => {}
-    };
-    score
+        i += 1;
+    }
 }
 
-fn format_generated_code(generated_code: &str) -> std::io::Result<String> {
-    use std::io::{Read, Write};
-
-    let mut process = Command::new("rustfmt")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        // rustfmt may post warnings about features not being enabled on stable rust
-        // these can be distracting and are irrevelant to the user, so we hide them
-        .stderr(Stdio::null())
-        .spawn()?;
-    let rustfmt_stdin = process.stdin.as_mut().unwrap();
-    let mut rustfmt_stdout = process.stdout.take().unwrap();
-    writeln!(rustfmt_stdin)?;
-    rustfmt_stdin.write_all(generated_code.as_bytes())?;
-
-    process.wait()?;
-    let mut formatted = String::new();
-    rustfmt_stdout.read_to_string(&mut formatted)?;
-
-    Ok(formatted)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus_xml::Node;
+
+    /// Render the first interface of `xml` as a proxy trait.
+    fn gen(xml: &str, struct_mode: bool) -> String {
+        let node = Node::try_from(xml).unwrap();
+        let iface = &node.interfaces()[0];
+        GenTrait {
+            interface: iface,
+            service: None,
+            path: None,
+            struct_mode,
+            lints: LintConfig::default(),
+        }
+        .to_string()
+    }
+
+    #[test]
+    fn type_complexity_scores_match_emitted_type() {
+        // Scores mirror clippy's walker: +nest per node, nest *= 10 descending into containers.
+        assert_eq!(estimate_type_complexity(b"ai", None), 11); // Vec<i32>
+        assert_eq!(estimate_type_complexity(b"a{sv}", None), 21); // HashMap<String, Value>
+        assert_eq!(estimate_type_complexity(b"a{sa{sv}}", None), 221);
+        assert_eq!(estimate_type_complexity(b"(ii)", None), 21); // (i32, i32)
+        assert_eq!(estimate_type_complexity(b"(i(ii))", None), 221);
+
+        // In struct_mode an interned struct collapses to a single named node.
+        let mut structs = Some(StructCollector::default());
+        let mut pos = 0;
+        walk_rust_type(b"(i(ii))", &mut pos, false, false, &mut structs);
+        let known = structs.as_ref();
+        assert_eq!(estimate_type_complexity(b"(ii)", known), 1); // Struct (inner)
+        assert_eq!(estimate_type_complexity(b"(i(ii))", known), 1); // Struct (outer)
+        assert_eq!(estimate_type_complexity(b"a(ii)", known), 11); // Vec<Struct>
+    }
+
+    #[test]
+    fn struct_mode_emits_named_and_deduplicated_structs() {
+        // `GetPair` and `SetPair` share the `(ii)` signature (one struct, reused); `Nested`
+        // introduces a nested `(ss)` plus its `(i(ss))` wrapper.
+        let xml = r#"<node>
+  <interface name="org.example.Structs">
+    <method name="GetPair">
+      <arg type="(ii)" direction="out"/>
+    </method>
+    <method name="Nested">
+      <arg type="(i(ss))" direction="out"/>
+    </method>
+    <method name="SetPair">
+      <arg type="(ii)" direction="in"/>
+    </method>
+  </interface>
+</node>"#;
+
+        let out = gen(xml, true);
+
+        // Methods are sorted by name: GetPair -> (ii) = Struct1, Nested -> (ss) = Struct2 and
+        // its wrapper (i(ss)) = Struct3, SetPair reuses Struct1.
+        assert_eq!(out.matches("pub struct Struct").count(), 3, "{out}");
+        assert!(out.contains("pub struct Struct1"), "{out}");
+        assert!(out.contains("pub struct Struct2"), "{out}");
+        assert!(out.contains("pub struct Struct3"), "{out}");
+        assert!(
+            out.contains("#[derive(zbus::zvariant::Type, serde::Serialize, serde::Deserialize)]"),
+            "{out}"
+        );
+        // The trait references the struct names, not anonymous tuples.
+        assert!(out.contains("zbus::Result<Struct1>"), "{out}");
+        assert!(out.contains("zbus::Result<Struct3>"), "{out}");
+        assert!(out.contains("&Struct1"), "{out}");
+        // The nested struct is referenced as a field of its wrapper.
+        assert!(out.contains("field_2: Struct2"), "{out}");
+    }
+
+    #[test]
+    fn flat_mode_keeps_anonymous_tuples() {
+        let xml = r#"<node>
+  <interface name="org.example.Structs">
+    <method name="GetPair">
+      <arg type="(ii)" direction="out"/>
+    </method>
+  </interface>
+</node>"#;
+
+        let out = gen(xml, false);
+        assert!(!out.contains("pub struct Struct"), "{out}");
+        assert!(out.contains("(i32, i32)"), "{out}");
+    }
 }